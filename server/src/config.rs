@@ -1,14 +1,63 @@
 use serde_yaml;
+use serde_json;
+use toml;
 
 use std::path::Path;
 use std::io::prelude::*;
+use std::io;
 use std::fs::File;
+use std::fmt;
+use std::env;
+use std::error::Error;
+use std::collections::HashMap;
+use std::ops::Deref;
 
 use std::result::Result;
 
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+
 use opcua_core::types::MessageSecurityMode;
 use constants;
 
+/// A string whose contents are hidden from `Debug` output so secrets such as
+/// passwords never leak through `{:?}` dumps or error logging. The raw value is
+/// still serialized and deserialized transparently so configuration files
+/// round-trip unchanged.
+#[derive(PartialEq, Clone)]
+pub struct MaskedString(String);
+
+impl MaskedString {
+    pub fn new<S>(s: S) -> MaskedString where S: Into<String> {
+        MaskedString(s.into())
+    }
+}
+
+impl Deref for MaskedString {
+    type Target = str;
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for MaskedString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "\"MASKED\"")
+    }
+}
+
+impl Serialize for MaskedString {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for MaskedString {
+    fn deserialize<D>(deserializer: D) -> Result<MaskedString, D::Error> where D: Deserializer<'de> {
+        let s = String::deserialize(deserializer)?;
+        Ok(MaskedString(s))
+    }
+}
+
 #[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
 pub struct TcpConfig {
     /// Timeout for hello on a session in seconds
@@ -17,6 +66,44 @@ pub struct TcpConfig {
     pub host: String,
     /// The port number of the service
     pub port: u16,
+    /// Interval in seconds between TCP keepalive probes
+    #[serde(default = "default_keepalive_interval_secs")]
+    pub keepalive_interval_secs: u32,
+    /// Idle time in seconds before TCP keepalive probes start
+    #[serde(default = "default_keepalive_secs")]
+    pub keepalive_secs: u32,
+    /// Whether to disable Nagle's algorithm (TCP_NODELAY) on accepted sockets
+    #[serde(default = "default_nodelay")]
+    pub nodelay: bool,
+    /// Interval in seconds between application-level heartbeats on idle sessions
+    #[serde(default = "default_heartbeat_interval_secs")]
+    pub heartbeat_interval_secs: u32,
+    /// Time in seconds without a heartbeat response before an idle session is reaped
+    #[serde(default = "default_heartbeat_timeout_secs")]
+    pub heartbeat_timeout_secs: u32,
+}
+
+impl TcpConfig {
+    pub fn is_valid(&self) -> bool {
+        let mut valid = true;
+        if self.heartbeat_interval_secs == 0 {
+            error!("Tcp configuration is invalid. Heartbeat interval must be greater than zero");
+            valid = false;
+        }
+        if self.heartbeat_timeout_secs <= self.heartbeat_interval_secs {
+            error!("Tcp configuration is invalid. Heartbeat timeout must be greater than the heartbeat interval");
+            valid = false;
+        }
+        if self.keepalive_interval_secs == 0 {
+            error!("Tcp configuration is invalid. Keepalive interval must be greater than zero");
+            valid = false;
+        }
+        if self.keepalive_secs == 0 {
+            error!("Tcp configuration is invalid. Keepalive idle time must be greater than zero");
+            valid = false;
+        }
+        valid
+    }
 }
 
 #[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
@@ -29,11 +116,15 @@ pub struct ServerEndpoint {
     pub security_policy: String,
     /// Security mode
     pub security_mode: String,
+    /// Transport used to expose the endpoint. "tcp" is the only transport the server serves;
+    /// "wss" is recognised and maps to the `opc.wss` scheme but is rejected by `is_valid()`.
+    #[serde(default = "default_transport")]
+    pub transport: String,
     /// Allow anonymous access (default false)
     pub anonymous: Option<bool>,
     /// Allow user name / password access
     pub user: Option<String>,
-    pub pass: Option<String>,
+    pub pass: Option<MaskedString>,
 }
 
 const DEFAULT_ENDPOINT_NAME: &'static str = "Default";
@@ -50,35 +141,75 @@ const SECURITY_MODE_NONE: &'static str = "None";
 const SECURITY_MODE_SIGN: &'static str = "Sign";
 const SECURITY_MODE_SIGN_AND_ENCRYPT: &'static str = "SignAndEncrypt";
 
+const DEFAULT_TRANSPORT: &'static str = TRANSPORT_TCP;
+const TRANSPORT_TCP: &'static str = "tcp";
+const TRANSPORT_WSS: &'static str = "wss";
+
+// Defaults used by `#[serde(default = ...)]` so configuration files written before these fields
+// existed continue to deserialize, falling back to the same values `ServerConfig::default` supplies.
+fn default_transport() -> String { DEFAULT_TRANSPORT.to_string() }
+fn default_min_password_length() -> usize { constants::DEFAULT_MIN_PASSWORD_LENGTH }
+fn default_keepalive_interval_secs() -> u32 { constants::DEFAULT_KEEPALIVE_INTERVAL_SECONDS }
+fn default_keepalive_secs() -> u32 { constants::DEFAULT_KEEPALIVE_SECONDS }
+fn default_nodelay() -> bool { constants::DEFAULT_TCP_NODELAY }
+fn default_heartbeat_interval_secs() -> u32 { constants::DEFAULT_HEARTBEAT_INTERVAL_SECONDS }
+fn default_heartbeat_timeout_secs() -> u32 { constants::DEFAULT_HEARTBEAT_TIMEOUT_SECONDS }
+
 impl ServerEndpoint {
-    pub fn new(name: &str, path: &str, anonymous: bool, user: &str, pass: &[u8], security_policy: &str, security_mode: &str) -> ServerEndpoint {
+    // The constructor mirrors the flat set of endpoint fields; the extra transport argument pushes it
+    // one past clippy's argument-count threshold, which is acceptable for this plain factory.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(name: &str, path: &str, anonymous: bool, user: &str, pass: &[u8], security_policy: &str, security_mode: &str, transport: &str) -> ServerEndpoint {
         ServerEndpoint {
             name: name.to_string(),
             path: path.to_string(),
             anonymous: Some(anonymous),
             user: if user.is_empty() { None } else { Some(user.to_string()) },
-            pass: if user.is_empty() { None } else { Some(String::from_utf8(pass.to_vec()).unwrap()) },
+            pass: if user.is_empty() { None } else { Some(MaskedString::new(String::from_utf8(pass.to_vec()).unwrap())) },
             security_policy: security_policy.to_string(),
             security_mode: security_mode.to_string(),
+            transport: transport.to_string(),
         }
     }
 
-    pub fn new_default(anonymous: bool, user: &str, pass: &[u8], security_policy: &str, security_mode: &str) -> ServerEndpoint {
-        ServerEndpoint::new(DEFAULT_ENDPOINT_NAME, DEFAULT_ENDPOINT_PATH, anonymous, user, pass, security_policy, security_mode)
+    pub fn new_default(anonymous: bool, user: &str, pass: &[u8], security_policy: &str, security_mode: &str, transport: &str) -> ServerEndpoint {
+        ServerEndpoint::new(DEFAULT_ENDPOINT_NAME, DEFAULT_ENDPOINT_PATH, anonymous, user, pass, security_policy, security_mode, transport)
     }
 
     pub fn default_anonymous() -> ServerEndpoint {
-        ServerEndpoint::new_default(true, "", &[], DEFAULT_SECURITY_POLICY, DEFAULT_SECURITY_MODE)
+        ServerEndpoint::new_default(true, "", &[], DEFAULT_SECURITY_POLICY, DEFAULT_SECURITY_MODE, DEFAULT_TRANSPORT)
     }
 
     pub fn default_user_pass(user: &str, pass: &[u8]) -> ServerEndpoint {
-        ServerEndpoint::new_default(false, user, pass, DEFAULT_SECURITY_POLICY, DEFAULT_SECURITY_MODE)
+        ServerEndpoint::new_default(false, user, pass, DEFAULT_SECURITY_POLICY, DEFAULT_SECURITY_MODE, DEFAULT_TRANSPORT)
     }
 
     /// Special config that turns on anonymous, user/pass and pki for sample code that wants everything available
-    /// Don't use in production.
+    /// Don't use in production. The sample password is long enough to satisfy the default minimum
+    /// password length so the sample server bootstraps through validation unchanged.
     pub fn default_sample() -> ServerEndpoint {
-        ServerEndpoint::new_default(true, "sample", "sample1".as_bytes(), DEFAULT_SECURITY_POLICY, DEFAULT_SECURITY_MODE)
+        ServerEndpoint::new_default(true, "sample", "sample1password1".as_bytes(), DEFAULT_SECURITY_POLICY, DEFAULT_SECURITY_MODE, DEFAULT_TRANSPORT)
+    }
+
+    /// Returns the endpoint URL scheme (`opc.tcp` or `opc.wss`) advertised through discovery
+    /// for this endpoint, based on its configured transport.
+    pub fn transport_scheme(&self) -> &'static str {
+        match self.transport.as_ref() {
+            TRANSPORT_WSS => "opc.wss",
+            _ => "opc.tcp",
+        }
+    }
+
+    /// Validates that, if this endpoint carries a user / password pair, the password is at
+    /// least `min_password_length` characters long.
+    pub fn is_password_valid(&self, min_password_length: usize) -> bool {
+        if let (Some(_), Some(ref pass)) = (self.user.as_ref(), self.pass.as_ref()) {
+            if pass.chars().count() < min_password_length {
+                error!("Endpoint {} is invalid. Password must be at least {} characters long", self.name, min_password_length);
+                return false;
+            }
+        }
+        true
     }
 
     pub fn is_valid(&self) -> bool {
@@ -104,6 +235,20 @@ impl ServerEndpoint {
             }
         }
 
+        match self.transport.as_ref() {
+            TRANSPORT_TCP => {}
+            TRANSPORT_WSS => {
+                // The server has no WebSocket message framing, so an endpoint using this transport
+                // cannot be served and is rejected.
+                error!("Endpoint {} is invalid. Transport \"wss\" (OPC UA over WebSocket) is not supported", self.name);
+                valid = false;
+            }
+            _ => {
+                error!("Endpoint {} is invalid. Transport \"{}\" is invalid. The only valid value is tcp", self.name, self.transport);
+                valid = false;
+            }
+        }
+
         if (&self.security_policy == SECURITY_POLICY_NONE && &self.security_mode != SECURITY_MODE_NONE) ||
             (&self.security_policy != SECURITY_POLICY_NONE && &self.security_mode == SECURITY_MODE_NONE) {
             error!("Endpoint {} is invalid. Security policy and security mode must both contain None or neither of them should.", self.name);
@@ -131,16 +276,25 @@ pub struct ServerConfig {
     pub pki_dir: String,
     /// Flag turns on or off discovery service
     pub discovery_service: bool,
-    /// tcp configuration information
-    pub tcp_config: TcpConfig,
-    /// Endpoints supported by the server
-    pub endpoints: Vec<ServerEndpoint>,
     /// Max array length in elements
     pub max_array_length: u32,
     /// Max string length in characters
     pub max_string_length: u32,
     /// Max bytestring length in bytes
     pub max_byte_string_length: u32,
+    /// Minimum length enforced on endpoint passwords
+    #[serde(default = "default_min_password_length")]
+    pub min_password_length: usize,
+    /// Hostnames (with optional `:port`) to advertise through discovery instead of the bound
+    /// `tcp_config.host`. One endpoint URL is generated per entry; when empty the bound host is used.
+    #[serde(default)]
+    pub advertise_addresses: Vec<String>,
+    // The table and array-of-table fields are declared last so TOML serialization, which requires
+    // all scalar keys to precede tables, succeeds.
+    /// tcp configuration information
+    pub tcp_config: TcpConfig,
+    /// Endpoints supported by the server
+    pub endpoints: Vec<ServerEndpoint>,
 }
 
 impl ServerConfig {
@@ -157,15 +311,22 @@ impl ServerConfig {
             product_uri: product_uri,
             discovery_service: true,
             pki_dir: "pki".to_string(),
+            max_array_length: constants::DEFAULT_MAX_ARRAY_LENGTH,
+            max_string_length: constants::DEFAULT_MAX_STRING_LENGTH,
+            max_byte_string_length: constants::DEFAULT_MAX_BYTE_STRING_LENGTH,
+            min_password_length: constants::DEFAULT_MIN_PASSWORD_LENGTH,
+            advertise_addresses: Vec::new(),
             tcp_config: TcpConfig {
                 host: hostname,
                 port: constants::DEFAULT_OPC_UA_SERVER_PORT,
                 hello_timeout: constants::DEFAULT_HELLO_TIMEOUT_SECONDS,
+                keepalive_interval_secs: constants::DEFAULT_KEEPALIVE_INTERVAL_SECONDS,
+                keepalive_secs: constants::DEFAULT_KEEPALIVE_SECONDS,
+                nodelay: constants::DEFAULT_TCP_NODELAY,
+                heartbeat_interval_secs: constants::DEFAULT_HEARTBEAT_INTERVAL_SECONDS,
+                heartbeat_timeout_secs: constants::DEFAULT_HEARTBEAT_TIMEOUT_SECONDS,
             },
             endpoints: endpoints,
-            max_array_length: constants::DEFAULT_MAX_ARRAY_LENGTH,
-            max_string_length: constants::DEFAULT_MAX_STRING_LENGTH,
-            max_byte_string_length: constants::DEFAULT_MAX_BYTE_STRING_LENGTH,
         }
     }
 
@@ -182,28 +343,30 @@ impl ServerConfig {
         ServerConfig::default(vec![ServerEndpoint::default_sample()])
     }
 
-    pub fn save(&self, path: &Path) -> Result<(), ()> {
-        if self.is_valid() {
-            let s = serde_yaml::to_string(&self).unwrap();
-            if let Ok(mut f) = File::create(path) {
-                if f.write_all(s.as_bytes()).is_ok() {
-                    return Ok(());
-                }
-            }
+    pub fn save(&self, path: &Path) -> Result<(), ConfigError> {
+        if !self.is_valid() {
+            return Err(ConfigError::ValidationFailure);
         }
-        Err(())
+        let s = match config_format(path)? {
+            ConfigFormat::Toml => toml::to_string(self).map_err(|e| ConfigError::SerializeError(e.to_string()))?,
+            ConfigFormat::Json => serde_json::to_string(self).map_err(|e| ConfigError::SerializeError(e.to_string()))?,
+            ConfigFormat::Yaml => serde_yaml::to_string(self).map_err(|e| ConfigError::SerializeError(e.to_string()))?,
+        };
+        let mut f = File::create(path)?;
+        f.write_all(s.as_bytes())?;
+        Ok(())
     }
 
-    pub fn load(path: &Path) -> Result<ServerConfig, ()> {
-        if let Ok(mut f) = File::open(path) {
-            let mut s = String::new();
-            if f.read_to_string(&mut s).is_ok() {
-                if let Ok(config) = serde_yaml::from_str(&s) {
-                    return Ok(config)
-                }
-            }
-        }
-        Err(())
+    pub fn load(path: &Path) -> Result<ServerConfig, ConfigError> {
+        let mut f = File::open(path)?;
+        let mut s = String::new();
+        f.read_to_string(&mut s)?;
+        let config = match config_format(path)? {
+            ConfigFormat::Toml => toml::from_str(&s).map_err(|e| ConfigError::ParseError(e.to_string()))?,
+            ConfigFormat::Json => serde_json::from_str(&s).map_err(|e| ConfigError::ParseError(e.to_string()))?,
+            ConfigFormat::Yaml => serde_yaml::from_str(&s).map_err(|e| ConfigError::ParseError(e.to_string()))?,
+        };
+        Ok(config)
     }
 
     pub fn is_valid(&self) -> bool {
@@ -212,10 +375,16 @@ impl ServerConfig {
             error!("Server configuration is invalid. It defines no endpoints");
             valid = false;
         }
+        if !self.tcp_config.is_valid() {
+            valid = false;
+        }
         for e in self.endpoints.iter() {
             if !e.is_valid() {
                 valid = false;
             }
+            if !e.is_password_valid(self.min_password_length) {
+                valid = false;
+            }
         }
         if self.max_array_length == 0 {
             error!("Server configuration is invalid.  Max array length is invalid");
@@ -229,10 +398,412 @@ impl ServerConfig {
             error!("Server configuration is invalid.  Max byte string length is invalid");
             valid = false;
         }
+        for address in self.advertise_addresses.iter() {
+            let (host, port) = split_host_port(address);
+            if let Some(port) = port {
+                if port.parse::<u16>().is_err() {
+                    error!("Server configuration is invalid. Advertise address \"{}\" has an invalid port", address);
+                    valid = false;
+                }
+            }
+            if host.trim_start_matches('[').trim_end_matches(']').is_empty() {
+                error!("Server configuration is invalid. Advertise address \"{}\" has an empty host", address);
+                valid = false;
+            }
+        }
         valid
     }
 
+    /// Builds the list of endpoint URLs advertised through discovery for the supplied endpoint.
+    /// When `advertise_addresses` is populated one URL is produced per address (using the
+    /// configured port when the entry omits one), otherwise the bound `tcp_config.host`/`port`
+    /// pair is used.
+    pub fn endpoint_urls(&self, endpoint: &ServerEndpoint) -> Vec<String> {
+        let scheme = endpoint.transport_scheme();
+        let addresses = if self.advertise_addresses.is_empty() {
+            vec![format!("{}:{}", self.tcp_config.host, self.tcp_config.port)]
+        } else {
+            self.advertise_addresses.iter().map(|address| {
+                let (host, port) = split_host_port(address);
+                match port {
+                    // The entry already carries a port, use it verbatim.
+                    Some(_) => address.clone(),
+                    // Append the bound port, bracketing bare IPv6 literals so the result stays parseable.
+                    None if host.contains(':') && !host.starts_with('[') => format!("[{}]:{}", host, self.tcp_config.port),
+                    None => format!("{}:{}", host, self.tcp_config.port),
+                }
+            }).collect()
+        };
+        addresses.iter().map(|address| format!("{}://{}{}", scheme, address, endpoint.path)).collect()
+    }
+
     pub fn message_security_mode() -> MessageSecurityMode {
         MessageSecurityMode::None
     }
+}
+
+/// Environment variable prefix recognised by [`ConfigBuilder::with_env`].
+pub const DEFAULT_ENV_PREFIX: &'static str = "OPCUA_SERVER";
+
+/// Separator used in environment variable and argument keys to descend into nested config fields,
+/// e.g. `OPCUA_SERVER__TCP_CONFIG__PORT` maps onto `tcp_config.port`.
+const ENV_PATH_SEPARATOR: &'static str = "__";
+
+/// Error returned by the layered configuration loader, distinguishing the reasons a load can fail
+/// so callers can report them individually.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The configuration file could not be read or written.
+    FileMissing(io::Error),
+    /// The configuration could not be parsed or deserialized into a `ServerConfig`.
+    ParseError(String),
+    /// The configuration could not be serialized to the target format.
+    SerializeError(String),
+    /// The resulting configuration failed `is_valid()`.
+    ValidationFailure,
+    /// The path carried an extension that maps to no known format.
+    UnsupportedExtension(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConfigError::FileMissing(ref err) => write!(f, "configuration file could not be read or written: {}", err),
+            ConfigError::ParseError(ref err) => write!(f, "configuration could not be parsed: {}", err),
+            ConfigError::SerializeError(ref err) => write!(f, "configuration could not be serialized: {}", err),
+            ConfigError::ValidationFailure => write!(f, "configuration failed validation"),
+            ConfigError::UnsupportedExtension(ref ext) => write!(f, "unsupported configuration file extension \"{}\"", ext),
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn description(&self) -> &str {
+        match *self {
+            ConfigError::FileMissing(_) => "configuration file could not be read or written",
+            ConfigError::ParseError(_) => "configuration could not be parsed",
+            ConfigError::SerializeError(_) => "configuration could not be serialized",
+            ConfigError::ValidationFailure => "configuration failed validation",
+            ConfigError::UnsupportedExtension(_) => "unsupported configuration file extension",
+        }
+    }
+}
+
+impl From<io::Error> for ConfigError {
+    fn from(err: io::Error) -> ConfigError {
+        ConfigError::FileMissing(err)
+    }
+}
+
+impl From<serde_yaml::Error> for ConfigError {
+    fn from(err: serde_yaml::Error) -> ConfigError {
+        ConfigError::ParseError(err.to_string())
+    }
+}
+
+/// Splits an advertised address into its host and optional port, respecting bracketed IPv6 literals
+/// (`[::1]:4840`). A bare, unbracketed host containing colons is treated as an IPv6 literal with no
+/// port rather than having its last colon misread as a port separator.
+fn split_host_port(address: &str) -> (&str, Option<&str>) {
+    if address.starts_with('[') {
+        if let Some(end) = address.find(']') {
+            let host = &address[..end + 1];
+            let rest = &address[end + 1..];
+            if rest.starts_with(':') {
+                return (host, Some(&rest[1..]));
+            }
+            return (host, None);
+        }
+        return (address, None);
+    }
+    if address.matches(':').count() == 1 {
+        let idx = address.find(':').unwrap();
+        return (&address[..idx], Some(&address[idx + 1..]));
+    }
+    (address, None)
+}
+
+/// Serialization format selected from a configuration file's extension.
+enum ConfigFormat {
+    Yaml,
+    Toml,
+    Json,
+}
+
+/// Maps a path's extension onto a [`ConfigFormat`]. A recognised extension selects its format, a
+/// path with no extension falls back to YAML (preserving the historical default), and any other
+/// extension is reported as [`ConfigError::UnsupportedExtension`].
+fn config_format(path: &Path) -> Result<ConfigFormat, ConfigError> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => Ok(ConfigFormat::Toml),
+        Some("json") => Ok(ConfigFormat::Json),
+        Some("yaml") | Some("yml") => Ok(ConfigFormat::Yaml),
+        Some(other) => Err(ConfigError::UnsupportedExtension(other.to_string())),
+        None => Ok(ConfigFormat::Yaml),
+    }
+}
+
+/// Layered configuration loader. Starts from a base (a YAML file or a `ServerConfig`), then overlays
+/// overrides from environment variables and an optional argument map before deserializing and
+/// validating the result. Override keys use `__` to descend into nested fields, and each value is
+/// parsed as YAML so numbers and booleans are typed correctly.
+pub struct ConfigBuilder {
+    value: serde_yaml::Value,
+}
+
+impl ConfigBuilder {
+    /// Starts a builder from a YAML configuration file.
+    pub fn from_file(path: &Path) -> Result<ConfigBuilder, ConfigError> {
+        let mut f = File::open(path)?;
+        let mut s = String::new();
+        f.read_to_string(&mut s)?;
+        let value = serde_yaml::from_str(&s)?;
+        Ok(ConfigBuilder { value: value })
+    }
+
+    /// Starts a builder from an in-memory `ServerConfig`, e.g. one of the `default_*` constructors.
+    pub fn from_config(config: &ServerConfig) -> Result<ConfigBuilder, ConfigError> {
+        let value = serde_yaml::to_value(config).map_err(|e| ConfigError::SerializeError(e.to_string()))?;
+        Ok(ConfigBuilder { value: value })
+    }
+
+    /// Overlays overrides sourced from environment variables beginning with `prefix` followed by the
+    /// path separator, e.g. `OPCUA_SERVER__TCP_CONFIG__PORT=4841`.
+    pub fn with_env(mut self, prefix: &str) -> ConfigBuilder {
+        let full_prefix = format!("{}{}", prefix, ENV_PATH_SEPARATOR);
+        for (key, value) in env::vars() {
+            if key.starts_with(&full_prefix) {
+                let path = parse_override_key(&key[prefix.len() + ENV_PATH_SEPARATOR.len()..]);
+                set_override(&mut self.value, &path, &value);
+            }
+        }
+        self
+    }
+
+    /// Overlays overrides from a parsed argument map whose keys use the same `__` path syntax.
+    pub fn with_args(mut self, args: &HashMap<String, String>) -> ConfigBuilder {
+        for (key, value) in args.iter() {
+            let path = parse_override_key(key);
+            set_override(&mut self.value, &path, value);
+        }
+        self
+    }
+
+    /// Deserializes and validates the accumulated configuration.
+    pub fn build(self) -> Result<ServerConfig, ConfigError> {
+        let config: ServerConfig = serde_yaml::from_value(self.value)?;
+        if config.is_valid() {
+            Ok(config)
+        } else {
+            Err(ConfigError::ValidationFailure)
+        }
+    }
+}
+
+/// Splits an override key on the path separator, lower-casing each segment to match the snake_case
+/// struct field names.
+fn parse_override_key(key: &str) -> Vec<String> {
+    key.split(ENV_PATH_SEPARATOR).map(|segment| segment.to_lowercase()).collect()
+}
+
+/// Applies a single override at `path`, parsing `raw` as YAML so numbers and booleans land as their
+/// typed forms (falling back to a plain string), creating intermediate mappings as needed.
+///
+/// Because the value is parsed as YAML, a string that happens to look like another scalar — `true`,
+/// `null`, `42` — is coerced to that type. To force a literal string for a textual field, quote the
+/// value (`OPCUA_SERVER__APPLICATION_NAME='true'`), which YAML parses back to the string `true`.
+fn set_override(node: &mut serde_yaml::Value, path: &[String], raw: &str) {
+    let leaf = serde_yaml::from_str(raw).unwrap_or_else(|_| serde_yaml::Value::String(raw.to_string()));
+    set_path(node, path, leaf);
+}
+
+fn set_path(node: &mut serde_yaml::Value, path: &[String], leaf: serde_yaml::Value) {
+    if path.is_empty() {
+        return;
+    }
+    if !node.is_mapping() {
+        *node = serde_yaml::Value::Mapping(serde_yaml::Mapping::new());
+    }
+    let map = node.as_mapping_mut().unwrap();
+    let key = serde_yaml::Value::String(path[0].clone());
+    if path.len() == 1 {
+        map.insert(key, leaf);
+    } else {
+        if !map.contains_key(&key) {
+            map.insert(key.clone(), serde_yaml::Value::Mapping(serde_yaml::Mapping::new()));
+        }
+        let child = map.get_mut(&key).unwrap();
+        set_path(child, &path[1..], leaf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn masked_string_hides_contents_from_debug_but_serializes_raw() {
+        let masked = MaskedString::new("hunter2");
+        // Debug must never expose the secret...
+        assert_eq!(format!("{:?}", masked), "\"MASKED\"");
+        // ...but Deref and serialization pass the raw value through unchanged.
+        assert_eq!(&*masked, "hunter2");
+        let serialized = serde_yaml::to_string(&masked).unwrap();
+        assert!(serialized.contains("hunter2"));
+        assert!(!serialized.contains("MASKED"));
+        let round_tripped: MaskedString = serde_yaml::from_str("hunter2").unwrap();
+        assert_eq!(&*round_tripped, "hunter2");
+    }
+
+    #[test]
+    fn default_sample_passes_validation() {
+        // The sample password must satisfy the default minimum length so sample bootstraps validate.
+        let config = ServerConfig::default_sample();
+        assert!(config.is_valid());
+    }
+
+    #[test]
+    fn transport_scheme_reflects_transport() {
+        let mut endpoint = ServerEndpoint::default_anonymous();
+        assert_eq!(endpoint.transport_scheme(), "opc.tcp");
+        endpoint.transport = TRANSPORT_WSS.to_string();
+        assert_eq!(endpoint.transport_scheme(), "opc.wss");
+    }
+
+    #[test]
+    fn endpoint_without_transport_key_defaults_to_tcp() {
+        // A YAML endpoint written before the transport field existed must still deserialize.
+        let yaml = "name: Default\npath: /\nsecurity_policy: None\nsecurity_mode: None\nanonymous: true\n";
+        let endpoint: ServerEndpoint = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(endpoint.transport, TRANSPORT_TCP);
+    }
+
+    #[test]
+    fn endpoint_urls_handle_ipv4_and_ipv6() {
+        let endpoint = ServerEndpoint::default_anonymous();
+        let mut config = ServerConfig::default(vec![endpoint.clone()]);
+        config.tcp_config.host = "0.0.0.0".to_string();
+        config.tcp_config.port = 4840;
+
+        // No advertised addresses falls back to the bound host/port.
+        assert_eq!(config.endpoint_urls(&endpoint), vec!["opc.tcp://0.0.0.0:4840/".to_string()]);
+
+        // Advertised host without a port inherits the bound port; a bare IPv6 literal is bracketed.
+        config.advertise_addresses = vec![
+            "public.example.com".to_string(),
+            "host.example.com:4855".to_string(),
+            "[::1]:4860".to_string(),
+            "fe80::1".to_string(),
+        ];
+        assert_eq!(config.endpoint_urls(&endpoint), vec![
+            "opc.tcp://public.example.com:4840/".to_string(),
+            "opc.tcp://host.example.com:4855/".to_string(),
+            "opc.tcp://[::1]:4860/".to_string(),
+            "opc.tcp://[fe80::1]:4840/".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn tcp_config_without_tuning_fields_uses_defaults() {
+        // A TcpConfig written before the tuning fields existed must still deserialize.
+        let yaml = "hello_timeout: 120\nhost: 127.0.0.1\nport: 4840\n";
+        let tcp_config: TcpConfig = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(tcp_config.keepalive_interval_secs, constants::DEFAULT_KEEPALIVE_INTERVAL_SECONDS);
+        assert_eq!(tcp_config.heartbeat_timeout_secs, constants::DEFAULT_HEARTBEAT_TIMEOUT_SECONDS);
+        assert_eq!(tcp_config.nodelay, constants::DEFAULT_TCP_NODELAY);
+    }
+
+    #[test]
+    fn short_password_is_rejected() {
+        let endpoint = ServerEndpoint::new_default(true, "user", "short".as_bytes(), DEFAULT_SECURITY_POLICY, DEFAULT_SECURITY_MODE, DEFAULT_TRANSPORT);
+        let mut config = ServerConfig::default(vec![endpoint]);
+        assert!(!config.is_valid());
+        // Relaxing the threshold lets the short password through.
+        config.min_password_length = 1;
+        assert!(config.is_valid());
+    }
+
+    #[test]
+    fn env_override_descends_nested_fields_and_types_leaf() {
+        env::set_var("OPCUA_SERVER__TCP_CONFIG__PORT", "4999");
+        env::set_var("OPCUA_SERVER__MIN_PASSWORD_LENGTH", "4");
+        let config = ConfigBuilder::from_config(&ServerConfig::default_anonymous()).unwrap()
+            .with_env(DEFAULT_ENV_PREFIX)
+            .build()
+            .unwrap();
+        env::remove_var("OPCUA_SERVER__TCP_CONFIG__PORT");
+        env::remove_var("OPCUA_SERVER__MIN_PASSWORD_LENGTH");
+        // The string "4999" is parsed as a typed integer, not left as a string.
+        assert_eq!(config.tcp_config.port, 4999);
+        assert_eq!(config.min_password_length, 4);
+    }
+
+    #[test]
+    fn arg_override_splits_on_separator() {
+        let mut args = HashMap::new();
+        args.insert("TCP_CONFIG__HOST".to_string(), "example.com".to_string());
+        let config = ConfigBuilder::from_config(&ServerConfig::default_anonymous()).unwrap()
+            .with_args(&args)
+            .build()
+            .unwrap();
+        assert_eq!(config.tcp_config.host, "example.com");
+    }
+
+    #[test]
+    fn quoted_override_stays_a_string() {
+        // A textual field whose value looks boolean must be quoted to stay a string (the YAML escape).
+        let mut args = HashMap::new();
+        args.insert("APPLICATION_NAME".to_string(), "'true'".to_string());
+        let config = ConfigBuilder::from_config(&ServerConfig::default_anonymous()).unwrap()
+            .with_args(&args)
+            .build()
+            .unwrap();
+        assert_eq!(config.application_name, "true");
+    }
+
+    #[test]
+    fn missing_file_reports_file_missing() {
+        let err = ConfigBuilder::from_file(&PathBuf::from("/no/such/config.yaml")).unwrap_err();
+        match err {
+            ConfigError::FileMissing(_) => {}
+            other => panic!("expected FileMissing, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn round_trips_through_every_format() {
+        let config = ServerConfig::default_sample();
+        for ext in ["yaml", "toml", "json"].iter() {
+            let path = env::temp_dir().join(format!("opcua_roundtrip.{}", ext));
+            config.save(&path).unwrap();
+            let loaded = ServerConfig::load(&path).unwrap();
+            assert_eq!(config, loaded, "round-trip failed for .{}", ext);
+        }
+    }
+
+    #[test]
+    fn unrecognized_extension_is_reported() {
+        match config_format(&PathBuf::from("server.ini")) {
+            Err(ConfigError::UnsupportedExtension(ref ext)) if ext == "ini" => {}
+            other => panic!("expected UnsupportedExtension(\"ini\"), got {:?}", other.map(|_| ())),
+        }
+        // A path with no extension keeps the historical YAML fallback.
+        assert!(config_format(&PathBuf::from("server")).is_ok());
+    }
+
+    #[test]
+    fn invalid_override_fails_validation() {
+        let mut args = HashMap::new();
+        args.insert("MIN_PASSWORD_LENGTH".to_string(), "0".to_string());
+        args.insert("ENDPOINTS".to_string(), "[]".to_string());
+        let err = ConfigBuilder::from_config(&ServerConfig::default_anonymous()).unwrap()
+            .with_args(&args)
+            .build()
+            .unwrap_err();
+        match err {
+            ConfigError::ValidationFailure => {}
+            other => panic!("expected ValidationFailure, got {:?}", other),
+        }
+    }
 }
\ No newline at end of file